@@ -0,0 +1,303 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use Source;
+use decoder::{DecoderError, ProbeResult};
+
+use tempfile::NamedTempFile;
+
+const OUTPUT_CHANNELS: u16 = 2;
+const OUTPUT_SAMPLE_RATE: u32 = 44_100;
+
+/// Decodes anything the host's `ffmpeg`/`ffprobe` binaries understand by
+/// shelling out to them, for formats none of the native decoders support
+/// (m4a, opus, aac, ...). Tried last in the probe chain, after every native
+/// format has declined. When the `symphonia` feature is enabled there is no
+/// probe chain and this decoder is never tried.
+pub struct FfmpegDecoder<R>
+    where R: Read + Seek
+{
+    // Kept alive only so the backing temp file isn't removed while ffmpeg
+    // still has it open for reading.
+    _temp_file: NamedTempFile,
+    child: Child,
+    total_duration: Option<Duration>,
+    buffer: Vec<u8>,
+    buffer_pos: usize,
+    // A read() from ffmpeg's stdout pipe is not guaranteed to land on a
+    // 2-byte sample boundary; a leftover trailing byte is held here and
+    // prepended to the next chunk instead of being dropped.
+    pending_byte: Option<u8>,
+    _marker: ::std::marker::PhantomData<R>,
+}
+
+impl<R> FfmpegDecoder<R>
+    where R: Read + Seek
+{
+    pub fn new(mut data: R) -> ProbeResult<FfmpegDecoder<R>, R> {
+        if !ffmpeg_available() {
+            return ProbeResult::NotRecognized(data);
+        }
+
+        let mut temp_file = match NamedTempFile::new() {
+            Ok(f) => f,
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        };
+
+        if let Err(e) = data.seek(SeekFrom::Start(0)) {
+            return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e)));
+        }
+        if let Err(e) = io::copy(&mut data, &mut temp_file) {
+            return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e)));
+        }
+
+        let total_duration = probe_duration(temp_file.path());
+
+        let child = Command::new("ffmpeg")
+            .args(&["-v", "error", "-i"])
+            .arg(temp_file.path())
+            .args(&["-f",
+                    "s16le",
+                    "-ac",
+                    &OUTPUT_CHANNELS.to_string(),
+                    "-ar",
+                    &OUTPUT_SAMPLE_RATE.to_string(),
+                    "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        };
+
+        ProbeResult::Decoded(FfmpegDecoder {
+                                 _temp_file: temp_file,
+                                 child: child,
+                                 total_duration: total_duration,
+                                 buffer: Vec::new(),
+                                 buffer_pos: 0,
+                                 pending_byte: None,
+                                 _marker: ::std::marker::PhantomData,
+                             })
+    }
+}
+
+impl<R> Drop for FfmpegDecoder<R>
+    where R: Read + Seek
+{
+    fn drop(&mut self) {
+        // Dropping the decoder mid-stream (e.g. skipping a track) must not
+        // leak the ffmpeg process or leave it as an unreaped zombie once it
+        // exits on its own.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn probe_duration(path: &::std::path::Path) -> Option<Duration> {
+    let output = Command::new("ffprobe")
+        .args(&["-v", "error", "-show_entries", "format=duration", "-of",
+                "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .output()
+        .ok()?;
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .map(Duration::from_secs_f64)
+}
+
+impl<R> Source for FfmpegDecoder<R>
+    where R: Read + Seek
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        OUTPUT_CHANNELS
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        OUTPUT_SAMPLE_RATE
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+impl<R> Iterator for FfmpegDecoder<R>
+    where R: Read + Seek
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        let stdout = self.child.stdout.as_mut().expect("ffmpeg stdout was piped");
+        next_sample(stdout, &mut self.buffer, &mut self.buffer_pos, &mut self.pending_byte)
+    }
+}
+
+/// Pulls the next decoded sample out of `stdout`, refilling `buffer` from
+/// `stdout` as needed.
+///
+/// Pulled out of `Iterator::next` so it can run against a fake `Read` in
+/// tests, since `FfmpegDecoder` itself can only wrap a real `ChildStdout`.
+fn next_sample<S>(stdout: &mut S,
+                   buffer: &mut Vec<u8>,
+                   buffer_pos: &mut usize,
+                   pending_byte: &mut Option<u8>)
+                   -> Option<i16>
+    where S: Read
+{
+    if *buffer_pos == buffer.len() {
+        if !refill_buffer(stdout, buffer, pending_byte) {
+            return None;
+        }
+        *buffer_pos = 0;
+    }
+
+    let sample = i16::from_le_bytes([buffer[*buffer_pos], buffer[*buffer_pos + 1]]);
+    *buffer_pos += 2;
+    Some(sample)
+}
+
+/// Reads from `stdout` until `buffer` holds at least one full sample (two
+/// bytes) or the stream is exhausted.
+///
+/// A single `read()` call on a pipe is not obligated to return an
+/// even/aligned number of bytes, so a lone trailing byte is carried over in
+/// `pending_byte` and prepended to the next read instead of being paired
+/// incorrectly (or indexed past the end of an empty buffer).
+fn refill_buffer<S>(stdout: &mut S, buffer: &mut Vec<u8>, pending_byte: &mut Option<u8>) -> bool
+    where S: Read
+{
+    loop {
+        let mut frame = [0u8; 4096];
+        let n = stdout.read(&mut frame).unwrap_or(0);
+        if n == 0 {
+            return false;
+        }
+
+        let mut new_buffer = match pending_byte.take() {
+            Some(pending) => {
+                let mut new_buffer = Vec::with_capacity(n + 1);
+                new_buffer.push(pending);
+                new_buffer.extend_from_slice(&frame[..n]);
+                new_buffer
+            },
+            None => frame[..n].to_vec(),
+        };
+
+        if new_buffer.len() % 2 != 0 {
+            *pending_byte = new_buffer.pop();
+        }
+
+        if new_buffer.is_empty() {
+            // The only byte we had so far went into pending_byte; read again
+            // rather than reporting a refill with nothing to consume.
+            continue;
+        }
+
+        *buffer = new_buffer;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::next_sample;
+
+    /// A fake `Read` that yields at most `chunk_size` bytes per call, to
+    /// exercise unaligned/short pipe reads without spawning a real process.
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        chunk_size: usize,
+    }
+
+    impl ChunkedReader {
+        fn new(data: Vec<u8>, chunk_size: usize) -> ChunkedReader {
+            ChunkedReader {
+                data: data,
+                pos: 0,
+                chunk_size: chunk_size,
+            }
+        }
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let remaining = self.data.len() - self.pos;
+            let n = remaining.min(self.chunk_size).min(buf.len());
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn decode_all(data: Vec<u8>, chunk_size: usize) -> Vec<i16> {
+        let mut reader = ChunkedReader::new(data, chunk_size);
+        let mut buffer = Vec::new();
+        let mut buffer_pos = 0;
+        let mut pending_byte = None;
+        let mut samples = Vec::new();
+
+        while let Some(sample) =
+            next_sample(&mut reader, &mut buffer, &mut buffer_pos, &mut pending_byte) {
+            samples.push(sample);
+        }
+
+        samples
+    }
+
+    fn samples_to_le_bytes(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn decodes_samples_delivered_one_byte_at_a_time() {
+        let samples: Vec<i16> = vec![1, -2, 32767];
+        let bytes = samples_to_le_bytes(&samples);
+
+        assert_eq!(decode_all(bytes, 1), samples);
+    }
+
+    #[test]
+    fn carries_a_trailing_odd_byte_across_reads() {
+        let samples: Vec<i16> = vec![1, -2, 3];
+        let bytes = samples_to_le_bytes(&samples);
+
+        assert_eq!(decode_all(bytes, 3), samples);
+    }
+
+    #[test]
+    fn a_true_trailing_odd_byte_at_eof_is_dropped_not_panicked() {
+        let mut bytes = samples_to_le_bytes(&[1]);
+        bytes.push(0xff);
+
+        assert_eq!(decode_all(bytes, 1), vec![1]);
+    }
+}
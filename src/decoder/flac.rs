@@ -0,0 +1,200 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use Source;
+use decoder::{DecoderError, ProbeResult, SeekError};
+
+use claxon::FlacReader;
+
+pub struct FlacDecoder<R>
+    where R: Read + Seek
+{
+    reader: Option<FlacReader<R>>,
+    current_block: Vec<i32>,
+    current_block_channels: u32,
+    current_block_off: usize,
+    bits_per_sample: u32,
+    sample_rate: u32,
+    channels: u32,
+    samples_read: u64,
+}
+
+impl<R> FlacDecoder<R>
+    where R: Read + Seek
+{
+    pub fn new(mut data: R) -> ProbeResult<FlacDecoder<R>, R> {
+        match is_flac(&mut data) {
+            Ok(true) => {},
+            Ok(false) => return ProbeResult::NotRecognized(data),
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        }
+
+        let reader = match FlacReader::new(data) {
+            Ok(reader) => reader,
+            Err(e) => return ProbeResult::Invalid(DecoderError::DecodeError(format!("{}", e))),
+        };
+
+        let info = reader.streaminfo();
+
+        ProbeResult::Decoded(FlacDecoder {
+                                 reader: Some(reader),
+                                 current_block: Vec::new(),
+                                 current_block_channels: info.channels,
+                                 current_block_off: 0,
+                                 bits_per_sample: info.bits_per_sample,
+                                 sample_rate: info.sample_rate,
+                                 channels: info.channels,
+                                 samples_read: 0,
+                             })
+    }
+
+    fn refill_block(&mut self) -> bool {
+        let reader = self.reader.as_mut().expect("FlacDecoder reader taken");
+        let mut frame_reader = reader.blocks();
+
+        match frame_reader.read_next_or_eof(Vec::new()) {
+            Ok(Some(block)) => {
+                let channels = block.channels();
+                let mut interleaved = Vec::with_capacity((block.len()) as usize);
+                for frame in 0..block.duration() {
+                    for channel in 0..channels {
+                        interleaved.push(block.sample(channel, frame));
+                    }
+                }
+                self.current_block = interleaved;
+                self.current_block_channels = channels;
+                self.current_block_off = 0;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Seeks using FLAC's decode-and-discard path: the stream is restarted
+    /// from the front and blocks are decoded (and thrown away) until the
+    /// target frame is reached, since claxon doesn't expose the seek table
+    /// directly.
+    pub fn seek(&mut self, pos: Duration) -> Result<Duration, SeekError> {
+        let target_frame = (pos.as_secs() * self.sample_rate as u64) +
+                            (pos.subsec_nanos() as u64 * self.sample_rate as u64) / 1_000_000_000;
+
+        let reader = self.reader.take().expect("FlacDecoder reader taken");
+        let mut inner = reader.into_inner();
+        inner.seek(SeekFrom::Start(0)).map_err(|e| SeekError::IoError(format!("{}", e)))?;
+
+        let mut reader = FlacReader::new(inner).map_err(|e| SeekError::IoError(format!("{}", e)))?;
+
+        let mut frames_skipped = 0u64;
+        let mut landed_frame = 0u64;
+        self.current_block = Vec::new();
+        self.current_block_off = 0;
+
+        {
+            let mut frame_reader = reader.blocks();
+            loop {
+                if frames_skipped >= target_frame {
+                    landed_frame = target_frame;
+                    break;
+                }
+                match frame_reader.read_next_or_eof(Vec::new()) {
+                    Ok(Some(block)) => {
+                        let duration = block.duration() as u64;
+                        if frames_skipped + duration > target_frame {
+                            let channels = block.channels();
+                            let skip_within_block = (target_frame - frames_skipped) as u32;
+                            let mut interleaved = Vec::new();
+                            for frame in skip_within_block..block.duration() {
+                                for channel in 0..channels {
+                                    interleaved.push(block.sample(channel, frame));
+                                }
+                            }
+                            self.current_block = interleaved;
+                            self.current_block_channels = channels;
+                            landed_frame = target_frame;
+                            frames_skipped += duration;
+                            break;
+                        }
+                        frames_skipped += duration;
+                    },
+                    _ => {
+                        // Ran out of stream before reaching the target frame;
+                        // we've landed wherever decoding stopped.
+                        landed_frame = frames_skipped;
+                        break;
+                    },
+                }
+            }
+        }
+
+        self.samples_read = frames_skipped * self.channels as u64;
+        self.reader = Some(reader);
+
+        Ok(Duration::from_secs_f64(landed_frame as f64 / self.sample_rate as f64))
+    }
+}
+
+fn is_flac<R: Read + Seek>(data: &mut R) -> Result<bool, ::std::io::Error> {
+    let stream_pos = data.seek(SeekFrom::Current(0))?;
+    let mut magic = [0u8; 4];
+    let looks_like_flac = data.read_exact(&mut magic).is_ok() && &magic == b"fLaC";
+    data.seek(SeekFrom::Start(stream_pos))?;
+    Ok(looks_like_flac)
+}
+
+fn scale_to_i16(sample: i32, bits_per_sample: u32) -> i16 {
+    if bits_per_sample >= 16 {
+        (sample >> (bits_per_sample - 16)) as i16
+    } else {
+        (sample << (16 - bits_per_sample)) as i16
+    }
+}
+
+impl<R> Source for FlacDecoder<R>
+    where R: Read + Seek
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.current_block.len() - self.current_block_off)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<R> Iterator for FlacDecoder<R>
+    where R: Read + Seek
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.current_block_off == self.current_block.len() {
+            if !self.refill_block() {
+                return None;
+            }
+        }
+
+        if self.current_block.is_empty() {
+            return None;
+        }
+
+        let raw = self.current_block[self.current_block_off];
+        self.current_block_off += 1;
+        if self.current_block_off % self.current_block_channels.max(1) as usize == 0 {
+            self.samples_read += self.current_block_channels as u64;
+        }
+        Some(scale_to_i16(raw, self.bits_per_sample))
+    }
+}
@@ -7,23 +7,95 @@ use std::time::Duration;
 
 use Source;
 
+#[cfg(feature = "ffmpeg_fallback")]
+mod ffmpeg;
 #[cfg(feature = "flac")]
 mod flac;
+#[cfg(feature = "mp3")]
+mod mp3;
+#[cfg(feature = "raw")]
+mod raw;
+#[cfg(feature = "symphonia")]
+mod symphonia;
 #[cfg(feature = "vorbis")]
 mod vorbis;
 #[cfg(feature = "wav")]
 mod wav;
 
+#[cfg(feature = "raw")]
+pub use self::raw::SampleFormat;
+
+/// A hint about the likely format of a `Decoder`'s input, so `new_with_hint`
+/// can try the most likely decoder first instead of blindly trial-decoding
+/// every format in turn.
+///
+/// Mirrors how streaming players already know the content-type from HTTP
+/// headers and want to avoid speculative parsing.
+#[derive(Debug, Clone, Default)]
+pub struct FormatHint {
+    extension: Option<String>,
+    mime_type: Option<String>,
+}
+
+impl FormatHint {
+    /// No hint: `new_with_hint` behaves exactly like `new`.
+    pub fn none() -> FormatHint {
+        FormatHint::default()
+    }
+
+    /// Hints based on a file extension, e.g. `"mp3"` or `"flac"` (with or
+    /// without the leading dot).
+    pub fn with_extension<S>(extension: S) -> FormatHint
+        where S: Into<String>
+    {
+        let extension = extension.into();
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        FormatHint {
+            extension: Some(extension),
+            mime_type: None,
+        }
+    }
+
+    /// Hints based on a MIME/content-type string, e.g. `"audio/mpeg"`.
+    pub fn with_mime_type<S>(mime_type: S) -> FormatHint
+        where S: Into<String>
+    {
+        FormatHint {
+            extension: None,
+            mime_type: Some(mime_type.into().to_lowercase()),
+        }
+    }
+
+    fn extension(&self) -> Option<&str> {
+        self.extension.as_ref().map(|e| e.as_str())
+    }
+
+    fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_ref().map(|m| m.as_str())
+    }
+
+    fn matches(&self, extensions: &[&str], mime_types: &[&str]) -> bool {
+        self.extension
+            .as_ref()
+            .map_or(false, |e| extensions.contains(&e.as_str())) ||
+        self.mime_type
+            .as_ref()
+            .map_or(false, |m| mime_types.contains(&m.as_str()))
+    }
+}
+
 /// Source of audio samples from decoding a file.
 ///
-/// Supports WAV, Vorbis and Flac.
-#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis"))]
+/// Supports WAV, Vorbis, Flac and MP3. When the `symphonia` feature is
+/// enabled, a single Symphonia-backed decoder is used instead and also
+/// covers AAC and other formats Symphonia's codec registry knows about.
+#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback"))]
 pub struct Decoder<R>(DecoderImpl<R>) where R: Read + Seek;
 
-#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis")))]
+#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback")))]
 pub struct Decoder<R>(::std::marker::PhantomData<R>);
 
-#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis"))]
+#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback"))]
 enum DecoderImpl<R>
     where R: Read + Seek
 {
@@ -33,6 +105,14 @@ enum DecoderImpl<R>
     Vorbis(vorbis::VorbisDecoder<R>),
     #[cfg(feature = "flac")]
     Flac(flac::FlacDecoder<R>),
+    #[cfg(feature = "mp3")]
+    Mp3(mp3::Mp3Decoder<R>),
+    #[cfg(feature = "symphonia")]
+    Symphonia(symphonia::SymphoniaDecoder<R>),
+    #[cfg(feature = "raw")]
+    Raw(raw::RawDecoder<R>),
+    #[cfg(feature = "ffmpeg_fallback")]
+    Ffmpeg(ffmpeg::FfmpegDecoder<R>),
 }
 
 impl<R> Decoder<R>
@@ -41,37 +121,208 @@ impl<R> Decoder<R>
     /// Builds a new decoder.
     ///
     /// Attempts to automatically detect the format of the source of data.
-    #[allow(unused_variables)]
     pub fn new(data: R) -> Result<Decoder<R>, DecoderError> {
+        Decoder::new_with_hint(data, FormatHint::none())
+    }
+
+    /// Builds a new decoder, passing `hint`'s extension through to
+    /// Symphonia's own probe so it can try the matching format reader first.
+    ///
+    /// Symphonia handles every format on its own, so there is no separate
+    /// per-format fallback chain to run here, and the `ffmpeg_fallback`
+    /// decoder is not tried either. Every error Symphonia reports, including
+    /// data in no format it recognizes, is returned as
+    /// `DecoderError::DecodeError`.
+    #[cfg(feature = "symphonia")]
+    #[allow(unused_variables)]
+    pub fn new_with_hint(data: R, hint: FormatHint) -> Result<Decoder<R>, DecoderError> {
+        symphonia::SymphoniaDecoder::new_with_hint(data, hint.extension(), hint.mime_type())
+            .map(|decoder| Decoder(DecoderImpl::Symphonia(decoder)))
+            .map_err(|e| DecoderError::DecodeError(format!("{}", e)))
+    }
+
+    /// Builds a new decoder, using `hint` to try the most likely format
+    /// first and skip straight to it on an exact match.
+    ///
+    /// Falls back to the full probe chain if the hint is absent or turns
+    /// out to be wrong.
+    #[cfg(not(feature = "symphonia"))]
+    #[allow(unused_variables, unused_mut)]
+    pub fn new_with_hint(mut data: R, hint: FormatHint) -> Result<Decoder<R>, DecoderError> {
         #[cfg(feature = "wav")]
-        let data = match wav::WavDecoder::new(data) {
-            Err(data) => data,
-            Ok(decoder) => {
-                return Ok(Decoder(DecoderImpl::Wav(decoder)));
-            },
+        let mut tried_wav = false;
+        #[cfg(feature = "flac")]
+        let mut tried_flac = false;
+        #[cfg(feature = "vorbis")]
+        let mut tried_vorbis = false;
+        #[cfg(feature = "mp3")]
+        let mut tried_mp3 = false;
+
+        #[cfg(feature = "wav")]
+        {
+            if hint.matches(&["wav", "wave"], &["audio/wav", "audio/x-wav", "audio/wave"]) {
+                tried_wav = true;
+                data = match wav::WavDecoder::new(data) {
+                    ProbeResult::Decoded(decoder) => {
+                        return Ok(Decoder(DecoderImpl::Wav(decoder)));
+                    },
+                    ProbeResult::Invalid(e) => return Err(e),
+                    ProbeResult::NotRecognized(data) => data,
+                };
+            }
+        }
+
+        #[cfg(feature = "flac")]
+        {
+            if hint.matches(&["flac"], &["audio/flac", "audio/x-flac"]) {
+                tried_flac = true;
+                data = match flac::FlacDecoder::new(data) {
+                    ProbeResult::Decoded(decoder) => {
+                        return Ok(Decoder(DecoderImpl::Flac(decoder)));
+                    },
+                    ProbeResult::Invalid(e) => return Err(e),
+                    ProbeResult::NotRecognized(data) => data,
+                };
+            }
+        }
+
+        #[cfg(feature = "vorbis")]
+        {
+            if hint.matches(&["ogg", "oga"], &["audio/ogg", "audio/vorbis"]) {
+                tried_vorbis = true;
+                data = match vorbis::VorbisDecoder::new(data) {
+                    ProbeResult::Decoded(decoder) => {
+                        return Ok(Decoder(DecoderImpl::Vorbis(decoder)));
+                    },
+                    ProbeResult::Invalid(e) => return Err(e),
+                    ProbeResult::NotRecognized(data) => data,
+                };
+            }
+        }
+
+        #[cfg(feature = "mp3")]
+        {
+            if hint.matches(&["mp3"], &["audio/mpeg", "audio/mp3"]) {
+                tried_mp3 = true;
+                data = match mp3::Mp3Decoder::new(data) {
+                    ProbeResult::Decoded(decoder) => {
+                        return Ok(Decoder(DecoderImpl::Mp3(decoder)));
+                    },
+                    ProbeResult::Invalid(e) => return Err(e),
+                    ProbeResult::NotRecognized(data) => data,
+                };
+            }
+        }
+
+        #[cfg(feature = "wav")]
+        let data = if tried_wav {
+            data
+        } else {
+            match wav::WavDecoder::new(data) {
+                ProbeResult::NotRecognized(data) => data,
+                ProbeResult::Decoded(decoder) => {
+                    return Ok(Decoder(DecoderImpl::Wav(decoder)));
+                },
+                ProbeResult::Invalid(e) => return Err(e),
+            }
         };
 
         #[cfg(feature = "flac")]
-        let data = match flac::FlacDecoder::new(data) {
-            Err(data) => data,
-            Ok(decoder) => {
-                return Ok(Decoder(DecoderImpl::Flac(decoder)));
-            },
+        let data = if tried_flac {
+            data
+        } else {
+            match flac::FlacDecoder::new(data) {
+                ProbeResult::NotRecognized(data) => data,
+                ProbeResult::Decoded(decoder) => {
+                    return Ok(Decoder(DecoderImpl::Flac(decoder)));
+                },
+                ProbeResult::Invalid(e) => return Err(e),
+            }
         };
 
         #[cfg(feature = "vorbis")]
-        let data = match vorbis::VorbisDecoder::new(data) {
-            Err(data) => data,
-            Ok(decoder) => {
-                return Ok(Decoder(DecoderImpl::Vorbis(decoder)));
+        let data = if tried_vorbis {
+            data
+        } else {
+            match vorbis::VorbisDecoder::new(data) {
+                ProbeResult::NotRecognized(data) => data,
+                ProbeResult::Decoded(decoder) => {
+                    return Ok(Decoder(DecoderImpl::Vorbis(decoder)));
+                },
+                ProbeResult::Invalid(e) => return Err(e),
+            }
+        };
+
+        #[cfg(feature = "mp3")]
+        let data = if tried_mp3 {
+            data
+        } else {
+            match mp3::Mp3Decoder::new(data) {
+                ProbeResult::NotRecognized(data) => data,
+                ProbeResult::Decoded(decoder) => {
+                    return Ok(Decoder(DecoderImpl::Mp3(decoder)));
+                },
+                ProbeResult::Invalid(e) => return Err(e),
+            }
+        };
+
+        #[cfg(feature = "ffmpeg_fallback")]
+        let data = match ffmpeg::FfmpegDecoder::new(data) {
+            ProbeResult::NotRecognized(data) => data,
+            ProbeResult::Decoded(decoder) => {
+                return Ok(Decoder(DecoderImpl::Ffmpeg(decoder)));
             },
+            ProbeResult::Invalid(e) => return Err(e),
         };
 
         Err(DecoderError::UnrecognizedFormat)
     }
+
+    /// Builds a new decoder for headerless PCM data.
+    ///
+    /// There is nothing to probe here: the caller must know the channel
+    /// count, sample rate and sample format up front, e.g. because the data
+    /// is being piped in from another process or streamed from a socket.
+    #[cfg(feature = "raw")]
+    pub fn new_raw(data: R, channels: u16, sample_rate: u32, sample_format: SampleFormat) -> Decoder<R> {
+        Decoder(DecoderImpl::Raw(raw::RawDecoder::new(data, channels, sample_rate, sample_format)))
+    }
+
+    /// Attempts to seek to a given position in the current source.
+    ///
+    /// Returns the position actually landed on, which may differ slightly
+    /// from `pos` since most formats can only land on a frame or granule
+    /// boundary.
+    #[cfg(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback"))]
+    pub fn seek(&mut self, pos: Duration) -> Result<Duration, SeekError> {
+        match self.0 {
+            #[cfg(feature = "wav")]
+            DecoderImpl::Wav(ref mut source) => source.seek(pos),
+            #[cfg(feature = "vorbis")]
+            DecoderImpl::Vorbis(ref mut source) => source.seek(pos),
+            #[cfg(feature = "flac")]
+            DecoderImpl::Flac(ref mut source) => source.seek(pos),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(_) => Err(SeekError::NotSupported),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(_) => Err(SeekError::NotSupported),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(_) => Err(SeekError::NotSupported),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(_) => Err(SeekError::NotSupported),
+        }
+    }
+
+    /// Attempts to seek to a given position in the current source.
+    ///
+    /// No format is enabled, so seeking is never supported.
+    #[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback")))]
+    pub fn seek(&mut self, _pos: Duration) -> Result<Duration, SeekError> {
+        Err(SeekError::NotSupported)
+    }
 }
 
-#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis")))]
+#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback")))]
 impl<R> Iterator for Decoder<R>
     where R: Read + Seek
 {
@@ -80,7 +331,7 @@ impl<R> Iterator for Decoder<R>
     fn next(&mut self) -> Option<i16> { None }
 }
 
-#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis"))]
+#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback"))]
 impl<R> Iterator for Decoder<R>
     where R: Read + Seek
 {
@@ -95,6 +346,14 @@ impl<R> Iterator for Decoder<R>
             DecoderImpl::Vorbis(ref mut source) => source.next(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref mut source) => source.next(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref mut source) => source.next(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref mut source) => source.next(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref mut source) => source.next(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref mut source) => source.next(),
         }
     }
 
@@ -107,11 +366,19 @@ impl<R> Iterator for Decoder<R>
             DecoderImpl::Vorbis(ref source) => source.size_hint(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref source) => source.size_hint(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref source) => source.size_hint(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref source) => source.size_hint(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref source) => source.size_hint(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref source) => source.size_hint(),
         }
     }
 }
 
-#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis")))]
+#[cfg(not(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback")))]
 impl<R> Source for Decoder<R>
     where R: Read + Seek
 {
@@ -121,7 +388,7 @@ impl<R> Source for Decoder<R>
     fn total_duration(&self) -> Option<Duration> { Some(Duration::default()) }
 }
 
-#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis"))]
+#[cfg(any(feature = "wav", feature = "flac", feature = "vorbis", feature = "mp3", feature = "symphonia", feature = "raw", feature = "ffmpeg_fallback"))]
 impl<R> Source for Decoder<R>
     where R: Read + Seek
 {
@@ -134,6 +401,14 @@ impl<R> Source for Decoder<R>
             DecoderImpl::Vorbis(ref source) => source.current_frame_len(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref source) => source.current_frame_len(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref source) => source.current_frame_len(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref source) => source.current_frame_len(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref source) => source.current_frame_len(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref source) => source.current_frame_len(),
         }
     }
 
@@ -146,6 +421,14 @@ impl<R> Source for Decoder<R>
             DecoderImpl::Vorbis(ref source) => source.channels(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref source) => source.channels(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref source) => source.channels(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref source) => source.channels(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref source) => source.channels(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref source) => source.channels(),
         }
     }
 
@@ -158,6 +441,14 @@ impl<R> Source for Decoder<R>
             DecoderImpl::Vorbis(ref source) => source.samples_rate(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref source) => source.samples_rate(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref source) => source.samples_rate(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref source) => source.samples_rate(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref source) => source.samples_rate(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref source) => source.samples_rate(),
         }
     }
 
@@ -170,6 +461,14 @@ impl<R> Source for Decoder<R>
             DecoderImpl::Vorbis(ref source) => source.total_duration(),
             #[cfg(feature = "flac")]
             DecoderImpl::Flac(ref source) => source.total_duration(),
+            #[cfg(feature = "mp3")]
+            DecoderImpl::Mp3(ref source) => source.total_duration(),
+            #[cfg(feature = "symphonia")]
+            DecoderImpl::Symphonia(ref source) => source.total_duration(),
+            #[cfg(feature = "raw")]
+            DecoderImpl::Raw(ref source) => source.total_duration(),
+            #[cfg(feature = "ffmpeg_fallback")]
+            DecoderImpl::Ffmpeg(ref source) => source.total_duration(),
         }
     }
 }
@@ -177,14 +476,21 @@ impl<R> Source for Decoder<R>
 /// Error that can happen when creating a decoder.
 #[derive(Debug, Clone)]
 pub enum DecoderError {
-    /// The format of the data has not been recognized.
+    /// The format of the data has not been recognized by any decoder.
     UnrecognizedFormat,
+    /// A decoder recognized the format but failed to decode it, e.g. a
+    /// truncated or corrupt file.
+    DecodeError(String),
+    /// An IO error occurred while probing or decoding.
+    IoError(String),
 }
 
 impl fmt::Display for DecoderError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &DecoderError::UnrecognizedFormat => write!(f, "Unrecognized format"),
+            &DecoderError::DecodeError(ref e) => write!(f, "Decode error: {}", e),
+            &DecoderError::IoError(ref e) => write!(f, "IO error: {}", e),
         }
     }
 }
@@ -193,6 +499,49 @@ impl Error for DecoderError {
     fn description(&self) -> &str {
         match self {
             &DecoderError::UnrecognizedFormat => "Unrecognized format",
+            &DecoderError::DecodeError(ref e) => e,
+            &DecoderError::IoError(ref e) => e,
+        }
+    }
+}
+
+/// Result of a per-format decoder constructor's attempt to claim a reader.
+///
+/// Distinguishes "this isn't my format, here's your reader back" from "this
+/// is my format but it's broken", so the trial chain in `Decoder::new` can
+/// tell a genuine decode failure apart from a simple format mismatch.
+pub(crate) enum ProbeResult<D, R> {
+    /// The decoder was built successfully.
+    Decoded(D),
+    /// The data doesn't look like this format; the reader is handed back.
+    NotRecognized(R),
+    /// The data looks like this format but failed to decode.
+    Invalid(DecoderError),
+}
+
+/// Error that can happen when seeking within a decoder.
+#[derive(Debug, Clone)]
+pub enum SeekError {
+    /// This decoder does not support seeking.
+    NotSupported,
+    /// An IO error occurred while seeking.
+    IoError(String),
+}
+
+impl fmt::Display for SeekError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &SeekError::NotSupported => write!(f, "Seeking is not supported by this decoder"),
+            &SeekError::IoError(ref e) => write!(f, "IO error while seeking: {}", e),
+        }
+    }
+}
+
+impl Error for SeekError {
+    fn description(&self) -> &str {
+        match self {
+            &SeekError::NotSupported => "Seeking is not supported by this decoder",
+            &SeekError::IoError(ref e) => e,
         }
     }
 }
@@ -0,0 +1,130 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use Source;
+use decoder::{DecoderError, ProbeResult};
+
+use minimp3::{Decoder as InnerDecoder, Error as Mp3Error, Frame};
+
+pub struct Mp3Decoder<R>
+    where R: Read + Seek
+{
+    decoder: InnerDecoder<R>,
+    current_frame: Frame,
+    current_frame_offset: usize,
+}
+
+impl<R> Mp3Decoder<R>
+    where R: Read + Seek
+{
+    pub fn new(mut data: R) -> ProbeResult<Mp3Decoder<R>, R> {
+        match is_mp3(data.by_ref()) {
+            Ok(true) => {},
+            Ok(false) => return ProbeResult::NotRecognized(data),
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        }
+        if let Err(e) = data.seek(SeekFrom::Start(0)) {
+            return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e)));
+        }
+        if let Err(e) = skip_id3v2_tag(&mut data) {
+            return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e)));
+        }
+
+        let mut decoder = InnerDecoder::new(data);
+        let current_frame = match decoder.next_frame() {
+            Ok(frame) => frame,
+            Err(e) => return ProbeResult::Invalid(DecoderError::DecodeError(format!("{:?}", e))),
+        };
+
+        ProbeResult::Decoded(Mp3Decoder {
+                                 decoder: decoder,
+                                 current_frame: current_frame,
+                                 current_frame_offset: 0,
+                             })
+    }
+}
+
+fn is_mp3<R>(mut data: R) -> Result<bool, ::std::io::Error>
+    where R: Read + Seek
+{
+    let stream_pos = data.seek(SeekFrom::Current(0))?;
+    skip_id3v2_tag(&mut data)?;
+    let result = InnerDecoder::new(data.by_ref()).next_frame().is_ok();
+    data.seek(SeekFrom::Start(stream_pos))?;
+    Ok(result)
+}
+
+/// Most real-world MP3s carry a leading ID3v2 tag, which minimp3 doesn't
+/// know how to skip on its own. If `data` starts with one, seek past it so
+/// frame sync starts at the first actual MPEG frame.
+fn skip_id3v2_tag<R>(data: &mut R) -> Result<(), ::std::io::Error>
+    where R: Read + Seek
+{
+    let stream_pos = data.seek(SeekFrom::Current(0))?;
+
+    let mut header = [0u8; 10];
+    let has_tag = data.read_exact(&mut header).is_ok() && &header[0..3] == b"ID3";
+
+    if !has_tag {
+        data.seek(SeekFrom::Start(stream_pos))?;
+        return Ok(());
+    }
+
+    // The size field is "synchsafe": 4 bytes, 7 significant bits each.
+    let tag_size = ((header[6] as u64 & 0x7f) << 21) | ((header[7] as u64 & 0x7f) << 14) |
+                   ((header[8] as u64 & 0x7f) << 7) | (header[9] as u64 & 0x7f);
+
+    data.seek(SeekFrom::Start(stream_pos + 10 + tag_size))?;
+    Ok(())
+}
+
+impl<R> Source for Mp3Decoder<R>
+    where R: Read + Seek
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.current_frame.data.len() - self.current_frame_offset)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.current_frame.channels as u16
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.current_frame.sample_rate as u32
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<R> Iterator for Mp3Decoder<R>
+    where R: Read + Seek
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.current_frame_offset == self.current_frame.data.len() {
+            match self.decoder.next_frame() {
+                Ok(frame) => self.current_frame = frame,
+                Err(Mp3Error::Eof) => return None,
+                Err(_) => return None,
+            }
+            self.current_frame_offset = 0;
+        }
+
+        let v = self.current_frame.data[self.current_frame_offset];
+        self.current_frame_offset += 1;
+        Some(v)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.current_frame.data.len() - self.current_frame_offset, None)
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::Read;
+use std::time::Duration;
+
+use Source;
+
+/// The layout of a headerless PCM stream passed to `Decoder::new_raw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    U8,
+    I16LE,
+    I16BE,
+    F32LE,
+    F32BE,
+}
+
+impl SampleFormat {
+    fn bytes_per_sample(&self) -> usize {
+        match *self {
+            SampleFormat::U8 => 1,
+            SampleFormat::I16LE | SampleFormat::I16BE => 2,
+            SampleFormat::F32LE | SampleFormat::F32BE => 4,
+        }
+    }
+}
+
+/// Decodes raw, headerless PCM samples, e.g. audio piped from another
+/// process or streamed from a socket.
+///
+/// Unlike the other decoders, there is nothing to probe: the caller must
+/// supply the channel count, sample rate and sample format up front.
+pub struct RawDecoder<R>
+    where R: Read
+{
+    reader: R,
+    channels: u16,
+    sample_rate: u32,
+    sample_format: SampleFormat,
+}
+
+impl<R> RawDecoder<R>
+    where R: Read
+{
+    pub fn new(data: R, channels: u16, sample_rate: u32, sample_format: SampleFormat) -> RawDecoder<R> {
+        RawDecoder {
+            reader: data,
+            channels: channels,
+            sample_rate: sample_rate,
+            sample_format: sample_format,
+        }
+    }
+}
+
+impl<R> Source for RawDecoder<R>
+    where R: Read
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<R> Iterator for RawDecoder<R>
+    where R: Read
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        let mut buf = [0u8; 4];
+        let n = self.sample_format.bytes_per_sample();
+
+        if self.reader.read_exact(&mut buf[..n]).is_err() {
+            return None;
+        }
+
+        Some(match self.sample_format {
+                 SampleFormat::U8 => ((buf[0] as i16) - 128) << 8,
+                 SampleFormat::I16LE => i16::from_le_bytes([buf[0], buf[1]]),
+                 SampleFormat::I16BE => i16::from_be_bytes([buf[0], buf[1]]),
+                 SampleFormat::F32LE => {
+                     f32_to_i16(f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+                 },
+                 SampleFormat::F32BE => {
+                     f32_to_i16(f32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]))
+                 },
+             })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+fn f32_to_i16(sample: f32) -> i16 {
+    (sample.max(-1.0).min(1.0) * i16::max_value() as f32) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{f32_to_i16, RawDecoder, SampleFormat};
+
+    #[test]
+    fn decodes_u8_i16_and_f32_samples() {
+        let u8_samples = vec![0u8, 128, 255];
+        let mut decoder = RawDecoder::new(Cursor::new(u8_samples), 1, 44_100, SampleFormat::U8);
+        assert_eq!(decoder.next(), Some(-32768));
+        assert_eq!(decoder.next(), Some(0));
+        assert_eq!(decoder.next(), Some(32512));
+
+        let i16le_samples = vec![0xff, 0x7f];
+        let mut decoder = RawDecoder::new(Cursor::new(i16le_samples), 1, 44_100, SampleFormat::I16LE);
+        assert_eq!(decoder.next(), Some(i16::max_value()));
+
+        let f32le_samples = 1.0f32.to_le_bytes().to_vec();
+        let mut decoder = RawDecoder::new(Cursor::new(f32le_samples), 1, 44_100, SampleFormat::F32LE);
+        assert_eq!(decoder.next(), Some(i16::max_value()));
+    }
+
+    #[test]
+    fn f32_to_i16_clamps_out_of_range_samples() {
+        assert_eq!(f32_to_i16(2.0), i16::max_value());
+        assert_eq!(f32_to_i16(-2.0), -i16::max_value());
+    }
+
+    #[test]
+    fn f32_to_i16_maps_full_scale() {
+        assert_eq!(f32_to_i16(0.0), 0);
+        assert_eq!(f32_to_i16(1.0), i16::max_value());
+        assert_eq!(f32_to_i16(-1.0), -i16::max_value());
+    }
+
+    #[test]
+    fn bytes_per_sample_matches_each_format() {
+        assert_eq!(SampleFormat::U8.bytes_per_sample(), 1);
+        assert_eq!(SampleFormat::I16LE.bytes_per_sample(), 2);
+        assert_eq!(SampleFormat::I16BE.bytes_per_sample(), 2);
+        assert_eq!(SampleFormat::F32LE.bytes_per_sample(), 4);
+        assert_eq!(SampleFormat::F32BE.bytes_per_sample(), 4);
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{Read, Seek};
+use std::time::Duration;
+
+use Source;
+
+extern crate symphonia;
+
+use self::symphonia::core::audio::{SampleBuffer, SignalSpec};
+use self::symphonia::core::codecs::{Decoder as CodecDecoder, DecoderOptions};
+use self::symphonia::core::errors::Error as SymphoniaError;
+use self::symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use self::symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions, ReadOnlySource};
+use self::symphonia::core::meta::MetadataOptions;
+use self::symphonia::core::probe::Hint;
+
+/// Decodes samples via Symphonia, supporting any container/codec pair that
+/// Symphonia's default probe and codec registry know about (Vorbis, MP3,
+/// FLAC, AAC, WAV, ...) behind a single code path.
+pub struct SymphoniaDecoder<R>
+    where R: Read + Seek
+{
+    format: Box<FormatReader>,
+    decoder: Box<CodecDecoder>,
+    track_id: u32,
+    spec: SignalSpec,
+    total_duration: Option<Duration>,
+    buffer: Vec<i16>,
+    buffer_offset: usize,
+    _marker: ::std::marker::PhantomData<R>,
+}
+
+impl<R> SymphoniaDecoder<R>
+    where R: Read + Seek + Send + 'static
+{
+    pub fn new(data: R) -> Result<SymphoniaDecoder<R>, SymphoniaError> {
+        SymphoniaDecoder::new_with_hint(data, None, None)
+    }
+
+    /// Like `new`, but `extension_hint` (e.g. `"mp3"`) and `mime_hint` (e.g.
+    /// `"audio/mpeg"`) are passed along to Symphonia's own probe so it can
+    /// try the matching format reader first.
+    pub fn new_with_hint(data: R,
+                          extension_hint: Option<&str>,
+                          mime_hint: Option<&str>)
+                          -> Result<SymphoniaDecoder<R>, SymphoniaError> {
+        let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(data)), MediaSourceStreamOptions::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = extension_hint {
+            hint.with_extension(extension);
+        }
+        if let Some(mime_type) = mime_hint {
+            hint.mime_type(mime_type);
+        }
+
+        let probed = self::symphonia::default::get_probe().format(&hint,
+                                                                mss,
+                                                                &FormatOptions::default(),
+                                                                &MetadataOptions::default())?;
+
+        let format = probed.format;
+        let track = first_supported_track(format.tracks())?;
+        let track_id = track.id;
+
+        let decoder = self::symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let spec = SignalSpec::new(track.codec_params.sample_rate.unwrap_or(44_100),
+                                    track.codec_params
+                                        .channels
+                                        .unwrap_or(self::symphonia::core::audio::Channels::FRONT_LEFT));
+
+        let total_duration = track.codec_params.n_frames.and_then(|n_frames| {
+            track.codec_params
+                .time_base
+                .map(|time_base| {
+                         let time = time_base.calc_time(n_frames);
+                         Duration::from_secs(time.seconds) +
+                         Duration::from_nanos((time.frac * 1_000_000_000.0) as u64)
+                     })
+        });
+
+        Ok(SymphoniaDecoder {
+               format: format,
+               decoder: decoder,
+               track_id: track_id,
+               spec: spec,
+               total_duration: total_duration,
+               buffer: Vec::new(),
+               buffer_offset: 0,
+               _marker: ::std::marker::PhantomData,
+           })
+    }
+
+    fn refill_buffer(&mut self) -> bool {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(_) => continue,
+            };
+
+            let mut sample_buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64,
+                                                              *decoded.spec());
+            sample_buffer.copy_interleaved_ref(decoded);
+
+            self.buffer = sample_buffer.samples().to_vec();
+            self.buffer_offset = 0;
+            return !self.buffer.is_empty();
+        }
+    }
+}
+
+fn first_supported_track(tracks: &[Track]) -> Result<&Track, SymphoniaError> {
+    tracks.iter()
+        .find(|t| t.codec_params.codec != self::symphonia::core::codecs::CODEC_TYPE_NULL)
+        .ok_or_else(|| SymphoniaError::Unsupported("no supported audio tracks"))
+}
+
+impl<R> Source for SymphoniaDecoder<R>
+    where R: Read + Seek + Send + 'static
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.buffer.len() - self.buffer_offset)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.spec.channels.count() as u16
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.spec.rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+impl<R> Iterator for SymphoniaDecoder<R>
+    where R: Read + Seek + Send + 'static
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.buffer_offset == self.buffer.len() {
+            if !self.refill_buffer() {
+                return None;
+            }
+        }
+
+        let sample = self.buffer[self.buffer_offset];
+        self.buffer_offset += 1;
+        Some(sample)
+    }
+}
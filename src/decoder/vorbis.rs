@@ -0,0 +1,116 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use Source;
+use decoder::{DecoderError, ProbeResult, SeekError};
+
+use lewton::inside_ogg::OggStreamReader;
+
+pub struct VorbisDecoder<R>
+    where R: Read + Seek
+{
+    reader: OggStreamReader<R>,
+    current_data: Vec<i16>,
+    current_data_offset: usize,
+}
+
+impl<R> VorbisDecoder<R>
+    where R: Read + Seek
+{
+    pub fn new(mut data: R) -> ProbeResult<VorbisDecoder<R>, R> {
+        match is_ogg(&mut data) {
+            Ok(true) => {},
+            Ok(false) => return ProbeResult::NotRecognized(data),
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        }
+
+        let reader = match OggStreamReader::new(data) {
+            Ok(reader) => reader,
+            Err(e) => return ProbeResult::Invalid(DecoderError::DecodeError(format!("{}", e))),
+        };
+
+        ProbeResult::Decoded(VorbisDecoder {
+                                 reader: reader,
+                                 current_data: Vec::new(),
+                                 current_data_offset: 0,
+                             })
+    }
+
+    /// Converts `pos` to an absolute granule position (`pos` in seconds times
+    /// the sample rate, rounded down to a whole sample) and seeks the
+    /// underlying ogg stream to it, returning the granule-rounded position
+    /// actually landed on.
+    pub fn seek(&mut self, pos: Duration) -> Result<Duration, SeekError> {
+        let sample_rate = self.reader.ident_hdr.audio_sample_rate as u64;
+        let absgp = (pos.as_secs() * sample_rate) +
+                    (pos.subsec_nanos() as u64 * sample_rate) / 1_000_000_000;
+
+        self.reader
+            .seek_absgp_pg(absgp)
+            .map_err(|e| SeekError::IoError(format!("{}", e)))?;
+
+        self.current_data.clear();
+        self.current_data_offset = 0;
+
+        Ok(Duration::from_secs_f64(absgp as f64 / sample_rate as f64))
+    }
+}
+
+fn is_ogg<R: Read + Seek>(data: &mut R) -> Result<bool, ::std::io::Error> {
+    let stream_pos = data.seek(SeekFrom::Current(0))?;
+    let mut magic = [0u8; 4];
+    let looks_like_ogg = data.read_exact(&mut magic).is_ok() && &magic == b"OggS";
+    data.seek(SeekFrom::Start(stream_pos))?;
+    Ok(looks_like_ogg)
+}
+
+impl<R> Source for VorbisDecoder<R>
+    where R: Read + Seek
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.current_data.len() - self.current_data_offset)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.reader.ident_hdr.audio_channels as u16
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<R> Iterator for VorbisDecoder<R>
+    where R: Read + Seek
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.current_data_offset == self.current_data.len() {
+            loop {
+                match self.reader.read_dec_packet_itl() {
+                    Ok(Some(ref packet)) if packet.is_empty() => continue,
+                    Ok(Some(packet)) => {
+                        self.current_data = packet;
+                        self.current_data_offset = 0;
+                        break;
+                    },
+                    Ok(None) | Err(_) => return None,
+                }
+            }
+        }
+
+        let sample = self.current_data[self.current_data_offset];
+        self.current_data_offset += 1;
+        Some(sample)
+    }
+}
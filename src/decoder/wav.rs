@@ -0,0 +1,136 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use Source;
+use decoder::{DecoderError, ProbeResult, SeekError};
+
+use hound::{WavReader, WavSpec};
+
+pub struct WavDecoder<R>
+    where R: Read + Seek
+{
+    reader: WavReader<R>,
+    spec: WavSpec,
+    total_samples: u64,
+    samples_read: u64,
+}
+
+impl<R> WavDecoder<R>
+    where R: Read + Seek
+{
+    pub fn new(mut data: R) -> ProbeResult<WavDecoder<R>, R> {
+        match is_wav(&mut data) {
+            Ok(true) => {},
+            Ok(false) => return ProbeResult::NotRecognized(data),
+            Err(e) => return ProbeResult::Invalid(DecoderError::IoError(format!("{}", e))),
+        }
+
+        let reader = match WavReader::new(data) {
+            Ok(reader) => reader,
+            Err(e) => return ProbeResult::Invalid(DecoderError::DecodeError(format!("{}", e))),
+        };
+
+        let spec = reader.spec();
+        let total_samples = reader.len() as u64;
+
+        ProbeResult::Decoded(WavDecoder {
+                                 reader: reader,
+                                 spec: spec,
+                                 total_samples: total_samples,
+                                 samples_read: 0,
+                             })
+    }
+
+    /// Seeks to the frame at or just before `pos` and returns the position
+    /// actually landed on.
+    pub fn seek(&mut self, pos: Duration) -> Result<Duration, SeekError> {
+        let sample_rate = self.spec.sample_rate as u64;
+        let channels = self.spec.channels as u64;
+
+        let target_frame = (pos.as_secs() * sample_rate) +
+                            (pos.subsec_nanos() as u64 * sample_rate) / 1_000_000_000;
+
+        self.reader
+            .seek(target_frame as u32)
+            .map_err(|e| SeekError::IoError(format!("{}", e)))?;
+
+        let landed_frame = target_frame.min(self.total_samples / channels);
+        self.samples_read = landed_frame * channels;
+
+        Ok(Duration::from_secs_f64(landed_frame as f64 / sample_rate as f64))
+    }
+}
+
+fn is_wav<R: Read + Seek>(data: &mut R) -> Result<bool, ::std::io::Error> {
+    let stream_pos = data.seek(SeekFrom::Current(0))?;
+    let mut magic = [0u8; 4];
+    let looks_like_wav = data.read_exact(&mut magic).is_ok() && &magic == b"RIFF";
+    data.seek(SeekFrom::Start(stream_pos))?;
+    Ok(looks_like_wav)
+}
+
+impl<R> Source for WavDecoder<R>
+    where R: Read + Seek
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((self.total_samples - self.samples_read) as usize)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.spec.channels
+    }
+
+    #[inline]
+    fn samples_rate(&self) -> u32 {
+        self.spec.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.total_samples / self.spec.channels as u64;
+        Some(Duration::from_secs_f64(frames as f64 / self.spec.sample_rate as f64))
+    }
+}
+
+impl<R> Iterator for WavDecoder<R>
+    where R: Read + Seek
+{
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        let sample = match self.spec.sample_format {
+            hound::SampleFormat::Int => {
+                self.reader
+                    .samples::<i32>()
+                    .next()
+                    .and_then(|s| s.ok())
+                    .map(|s| match self.spec.bits_per_sample {
+                             8 => (s as i16 - 128) << 8,
+                             16 => s as i16,
+                             _ => (s >> (self.spec.bits_per_sample - 16)) as i16,
+                         })
+            },
+            hound::SampleFormat::Float => {
+                self.reader
+                    .samples::<f32>()
+                    .next()
+                    .and_then(|s| s.ok())
+                    .map(|s| (s.max(-1.0).min(1.0) * i16::max_value() as f32) as i16)
+            },
+        };
+
+        if sample.is_some() {
+            self.samples_read += 1;
+        }
+        sample
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.total_samples - self.samples_read) as usize;
+        (remaining, Some(remaining))
+    }
+}